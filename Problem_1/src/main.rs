@@ -1,77 +1,561 @@
-use nix::unistd::{fork, ForkResult, dup2, close, pipe, execvp};
-use nix::sys::wait::{waitpid, WaitStatus};
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{fork, ForkResult, dup, dup2, close, pipe, execvp, getpid, setpgid, tcgetpgrp, tcsetpgrp, write, Pid};
 use std::ffi::CString;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::os::fd::AsRawFd;
-use std::os::unix::io::{RawFd, IntoRawFd};
-use std::fs::File;
-use std::env; 
-
-#[derive(Debug)]
-enum InputType {
-    SingleCommand(Command),
-    Pipe(Vec<Command>),
-    InputRedirect(Command, String),
-    OutputRedirect(Command, String),
-    BiRedirect(Command, String, String),
-}
+use std::os::unix::io::{RawFd, IntoRawFd, FromRawFd};
+use std::fs::{File, OpenOptions};
+use std::env;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 struct Command {
     pub program: String,
     pub args: Vec<String>,
-    pub input_file: Option<String>,
-    pub output_file: Option<String>,
+    pub redirects: Vec<Redirect>,
 }
 
+/// 리다이렉션이 파일을 읽기용으로 열지(`<`), 쓰기용으로 열지(`>`/`>>`)를
+/// 명시적으로 표시한다. `fd == 0`으로 방향을 추측하면 `3< in.txt`처럼 fd를
+/// 직접 지정한 입력 리다이렉션을 놓치므로, 연산자를 파싱하는 시점에 이미
+/// 알고 있는 방향을 그대로 들고 다닌다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RedirDirection {
+    In,
+    Out,
+}
 
-/// 리다이렉션이 포함될 수 있는 단일 명령어 문자열을 파싱하여,
-/// 프로그램, 인자, input_file, output_file를 추출하는 함수.
-fn parse_redir_command(input: &str) -> Option<Command> {
-    // 우선 전체를 공백 기준으로 토큰화
-    let tokens: Vec<&str> = input.trim().split_whitespace().collect();
-    if tokens.is_empty() {
+/// 리다이렉션 하나를 표현. `fd`는 자식 프로세스에서 바꿔치기할 파일 디스크립터,
+/// `target`은 그 fd가 향할 곳(파일 경로 또는 다른 fd), `append`는 `>>`처럼
+/// 기존 내용 뒤에 이어쓸지 여부, `direction`은 그 경로를 읽기용으로 열지
+/// 쓰기용으로 열지를 나타낸다(`RedirTarget::Fd`에는 의미가 없다).
+#[derive(Debug, Clone)]
+struct Redirect {
+    pub fd: RawFd,
+    pub target: RedirTarget,
+    pub append: bool,
+    pub direction: RedirDirection,
+}
+
+#[derive(Debug, Clone)]
+enum RedirTarget {
+    Path(String),
+    Fd(RawFd),
+}
+
+/// `parse_redir_command`이 이해하는 리다이렉션 연산자 한 종류.
+/// `>>`/`N>`/`N>>`/`N>&M` 모두 여기로 들어온 뒤 둘 중 하나로 정리된다:
+/// 파일로 열어야 하는 경우(`ToPath`)와, 이미 열려 있는 다른 fd를 그대로
+/// dup해야 하는 경우(`ToFd`, 예: `2>&1`).
+enum RedirSpec {
+    ToPath { fd: RawFd, append: bool, direction: RedirDirection },
+    ToFd { fd: RawFd, target_fd: RawFd },
+}
+
+/// 토큰 하나가 리다이렉션 연산자인지 검사하고, 맞다면 해석한 스펙을 돌려준다.
+/// `<`/`>`/`>>`/`2>`/`2>>`/`2>&1`처럼 앞에 fd 숫자가 붙을 수도, 안 붙을 수도 있다.
+fn parse_redir_operator(tok: &str) -> Option<RedirSpec> {
+    let digit_len = tok.chars().take_while(|c| c.is_ascii_digit()).count();
+    let explicit_fd: Option<RawFd> = if digit_len > 0 {
+        Some(tok[..digit_len].parse().ok()?)
+    } else {
+        None
+    };
+    let rest = &tok[digit_len..];
+
+    match rest {
+        "<" => Some(RedirSpec::ToPath { fd: explicit_fd.unwrap_or(0), append: false, direction: RedirDirection::In }),
+        ">" => Some(RedirSpec::ToPath { fd: explicit_fd.unwrap_or(1), append: false, direction: RedirDirection::Out }),
+        ">>" => Some(RedirSpec::ToPath { fd: explicit_fd.unwrap_or(1), append: true, direction: RedirDirection::Out }),
+        _ if rest.starts_with(">&") => {
+            let target_fd: RawFd = rest[2..].parse().ok()?;
+            Some(RedirSpec::ToFd { fd: explicit_fd.unwrap_or(1), target_fd })
+        }
+        _ => None,
+    }
+}
+
+/// 리다이렉션 연산자 하나의 길이(문자 수)를 계산한다. 토큰화 단계에서
+/// `ls>out`처럼 단어에 연산자가 들러붙은 경우를 잘라내는 데 쓰인다.
+/// fd 숫자 접두사는 "새 토큰이 시작하는 자리"에서만 연산자로 인정한다
+/// (`echo2>out`은 프로그램 이름 `echo2` 뒤에 `>out`이 붙은 것으로 본다).
+fn match_redir_operator(chars: &[char], start: usize, at_token_start: bool) -> Option<usize> {
+    let mut i = start;
+    if at_token_start {
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i >= chars.len() {
         return None;
     }
+    match chars[i] {
+        '<' => Some(i + 1 - start),
+        '>' => {
+            let mut j = i + 1;
+            if j < chars.len() && chars[j] == '>' {
+                j += 1;
+            }
+            if j < chars.len() && chars[j] == '&' {
+                let mut k = j + 1;
+                while k < chars.len() && chars[k].is_ascii_digit() {
+                    k += 1;
+                }
+                if k > j + 1 {
+                    return Some(k - start);
+                }
+            }
+            Some(j - start)
+        }
+        _ => None,
+    }
+}
 
-    let mut program = String::new();
-    let mut args = Vec::new();
-    let mut input_file: Option<String> = None;
-    let mut output_file: Option<String> = None;
+/// `$(...)` 안의 내용을 닫는 `)`까지 읽어들인다. 중첩된 괄호와 그 안의
+/// 따옴표(`'...'`, `"..."`)까지 감안해서 짝을 맞춘다. `start`는 여는 `(`
+/// 바로 다음 글자를 가리켜야 한다. 닫는 괄호를 찾으면 내용과 그 다음
+/// 위치를 돌려준다.
+fn scan_balanced_parens(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut depth = 1;
+    let mut i = start;
+    let mut in_single = false;
+    let mut in_double = false;
+    while i < chars.len() {
+        let c = chars[i];
+        if in_single {
+            if c == '\'' {
+                in_single = false;
+            }
+        } else if in_double {
+            if c == '\\' && i + 1 < chars.len() {
+                i += 1;
+            } else if c == '"' {
+                in_double = false;
+            }
+        } else {
+            match c {
+                '\'' => in_single = true,
+                '"' => in_double = true,
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((chars[start..i].iter().collect(), i + 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 백틱 명령 치환(`` `cmd` ``) 안의 내용을 닫는 백틱까지 읽어들인다.
+/// `start`는 여는 백틱 바로 다음 글자를 가리켜야 한다. `` \` ``, `\\`, `\$`만
+/// 이스케이프로 인정한다.
+fn scan_backtick(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut i = start;
+    let mut inner = String::new();
+    while i < chars.len() && chars[i] != '`' {
+        if chars[i] == '\\' && i + 1 < chars.len() && matches!(chars[i + 1], '`' | '\\' | '$') {
+            inner.push(chars[i + 1]);
+            i += 2;
+        } else {
+            inner.push(chars[i]);
+            i += 1;
+        }
+    }
+    if i >= chars.len() {
+        return None;
+    }
+    Some((inner, i + 1))
+}
+
+/// 자식 프로세스를 하나 띄워 `cmd_line`을 실행하고, 그 표준 출력을 파이프로
+/// 받아 문자열로 모아 돌려준다(맨 끝 개행 제거). 자식이 파이프 버퍼보다 많이
+/// 써서 멈추는 걸 피하려면 `waitpid`보다 먼저 읽기 끝을 끝까지 드레인해야
+/// 한다: `read_to_end`는 데이터가 오는 대로 받고 자식이 쓰기 끝을 전부
+/// 닫아야(=끝나야) EOF로 끝나므로, 자식이 도는 동안 자연히 같이 드레인된다.
+fn capture_command_output(cmd_line: &str) -> String {
+    let Ok((r, w)) = pipe() else {
+        return String::new();
+    };
+    let r = r.into_raw_fd();
+    let w = w.into_raw_fd();
 
+    match unsafe { fork() } {
+        Ok(ForkResult::Child) => {
+            close(r).ok();
+            dup2(w, 1).expect("Failed to redirect command substitution output");
+            close(w).ok();
+
+            let mut jobs = JobTable::new();
+            let pgid = getpid();
+            if let Some(list) = parse_input(cmd_line) {
+                eval_list(&list, &mut jobs, pgid);
+            }
+            std::process::exit(0);
+        }
+        Ok(ForkResult::Parent { child }) => {
+            close(w).ok();
+            let mut file = unsafe { File::from_raw_fd(r) };
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf).ok();
+            waitpid(child, None).ok();
+            String::from_utf8_lossy(&buf).trim_end_matches('\n').to_string()
+        }
+        Err(_) => {
+            close(r).ok();
+            close(w).ok();
+            String::new()
+        }
+    }
+}
+
+/// 치환된 명령의 출력을 치환 지점에 이어붙인다. 큰따옴표 안이면 내부
+/// 공백을 그대로 보존해 한 단어로 남기고, 밖이면 공백 기준으로 필드를
+/// 나눠 다시 공백 하나로 이어붙여서 뒤따라올 토큰화 단계가 자연히 여러
+/// 단어로 쪼개지게 만든다(셸의 단어 쪼개기를 흉내).
+fn splice_captured(out: &mut String, captured: &str, in_double_quotes: bool) {
+    if in_double_quotes {
+        out.push_str(captured);
+    } else {
+        let fields: Vec<&str> = captured.split_whitespace().collect();
+        out.push_str(&fields.join(" "));
+    }
+}
+
+/// 토큰화 전에 줄 전체를 훑어 `$(...)`와 백틱 명령 치환을 찾아 실행하고,
+/// 그 결과로 치환한 새 줄을 돌려준다. 작은따옴표 안은 리터럴이라 건드리지
+/// 않는다. (치환 결과 자체에 따옴표나 연산자 문자가 들어 있으면 뒤따르는
+/// 토큰화 단계에서 다시 해석될 수 있다는 점은, 이 셸의 단순화로 감수한다.)
+fn expand_command_substitutions(input: &str) -> Option<String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::new();
     let mut i = 0;
-    while i < tokens.len() {
-        match tokens[i] {
-            "<" => {
-                // 다음 토큰이 파일명
-                if i + 1 < tokens.len() {
-                    input_file = Some(tokens[i+1].to_string());
-                    i += 2;
-                } else {
-                    eprintln!("Syntax error: no input file after '<'");
+    let mut in_single = false;
+    let mut in_double = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_single {
+            out.push(c);
+            if c == '\'' {
+                in_single = false;
+            }
+            i += 1;
+            continue;
+        }
+        if c == '\'' && !in_double {
+            in_single = true;
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        if c == '"' {
+            in_double = !in_double;
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        if c == '\\' && i + 1 < chars.len() {
+            out.push(c);
+            out.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '$' && i + 1 < chars.len() && chars[i + 1] == '(' {
+            let Some((inner, next)) = scan_balanced_parens(&chars, i + 2) else {
+                eprintln!("Syntax error: unterminated command substitution");
+                return None;
+            };
+            let captured = capture_command_output(&inner);
+            splice_captured(&mut out, &captured, in_double);
+            i = next;
+            continue;
+        }
+        if c == '`' {
+            let Some((inner, next)) = scan_backtick(&chars, i + 1) else {
+                eprintln!("Syntax error: unterminated backtick command substitution");
+                return None;
+            };
+            let captured = capture_command_output(&inner);
+            splice_captured(&mut out, &captured, in_double);
+            i = next;
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+
+    Some(out)
+}
+
+/// 단어 한 조각. 작은따옴표나 백슬래시로 이스케이프된 부분은 `Literal`로
+/// 남아 확장을 건너뛰고, 그 외(따옴표 밖 / 큰따옴표 안)는 `Expandable`로
+/// 남아 `~`/`$VAR` 확장의 대상이 된다. 한 단어가 `"$HOME"/bin`처럼 여러
+/// 조각이 이어붙어 만들어질 수 있어 `Vec<WordPart>`로 들고 있는다.
+#[derive(Debug, Clone)]
+enum WordPart {
+    Literal(String),
+    Expandable(String),
+}
+
+/// 줄 전체를 한 번 훑어 만든 토큰. 연산자(`|`, `&&`, `||`, `;`, 리다이렉션)는
+/// 따옴표 밖에서만 인식되고, 단어는 여러 `WordPart`의 연속으로 표현된다.
+#[derive(Debug, Clone)]
+enum Token {
+    Word(Vec<WordPart>),
+    Redir(String),
+    Pipe,
+    AndAnd,
+    OrOr,
+    Semi,
+}
+
+fn flush_buf(buf: &mut String, parts: &mut Vec<WordPart>) {
+    if !buf.is_empty() {
+        parts.push(WordPart::Expandable(std::mem::take(buf)));
+    }
+}
+
+fn flush_word(buf: &mut String, parts: &mut Vec<WordPart>, tokens: &mut Vec<Token>) {
+    flush_buf(buf, parts);
+    if !parts.is_empty() {
+        tokens.push(Token::Word(std::mem::take(parts)));
+    }
+}
+
+/// 상태 기계 기반 렉서. 작은따옴표(리터럴, 확장 없음), 큰따옴표(확장은 되지만
+/// 단어 쪼개짐은 없음), 백슬래시 이스케이프를 다루고, 파이프와 리다이렉션
+/// 연산자는 단어에 들러붙어 있어도(`ls>out`, `2>&1`) 별도 토큰으로 떼어낸다.
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut parts: Vec<WordPart> = Vec::new();
+    let mut buf = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => {
+                flush_word(&mut buf, &mut parts, &mut tokens);
+                i += 1;
+            }
+            '\'' => {
+                // 작은따옴표: 닫는 따옴표까지 전부 리터럴, 확장 없음.
+                flush_buf(&mut buf, &mut parts);
+                i += 1;
+                let start = i;
+                while i < chars.len() && chars[i] != '\'' {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    eprintln!("Syntax error: unterminated single quote");
+                    return None;
+                }
+                parts.push(WordPart::Literal(chars[start..i].iter().collect()));
+                i += 1;
+            }
+            '"' => {
+                // 큰따옴표: 단어 쪼개짐은 없지만 $VAR 확장은 그대로 적용됨.
+                // \", \\, \$만 이스케이프로 인정.
+                flush_buf(&mut buf, &mut parts);
+                i += 1;
+                let mut content = String::new();
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' && i + 1 < chars.len() && matches!(chars[i + 1], '"' | '\\' | '$') {
+                        // 이스케이프된 글자는 확장되지 않도록 Literal 조각으로 따로 넣는다.
+                        if !content.is_empty() {
+                            parts.push(WordPart::Expandable(std::mem::take(&mut content)));
+                        }
+                        parts.push(WordPart::Literal(chars[i + 1].to_string()));
+                        i += 2;
+                    } else {
+                        content.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                if i >= chars.len() {
+                    eprintln!("Syntax error: unterminated double quote");
                     return None;
                 }
+                parts.push(WordPart::Expandable(content));
+                i += 1;
             }
-            ">" => {
-                if i + 1 < tokens.len() {
-                    output_file = Some(tokens[i+1].to_string());
+            '\\' if i + 1 < chars.len() => {
+                // 따옴표 밖 백슬래시: 다음 글자 하나를 그대로 리터럴로 넣음.
+                flush_buf(&mut buf, &mut parts);
+                parts.push(WordPart::Literal(chars[i + 1].to_string()));
+                i += 2;
+            }
+            '|' => {
+                flush_word(&mut buf, &mut parts, &mut tokens);
+                if i + 1 < chars.len() && chars[i + 1] == '|' {
+                    tokens.push(Token::OrOr);
                     i += 2;
                 } else {
-                    eprintln!("Syntax error: no output file after '>'");
-                    return None;
+                    tokens.push(Token::Pipe);
+                    i += 1;
+                }
+            }
+            '&' if i + 1 < chars.len() && chars[i + 1] == '&' => {
+                flush_word(&mut buf, &mut parts, &mut tokens);
+                tokens.push(Token::AndAnd);
+                i += 2;
+            }
+            ';' => {
+                flush_word(&mut buf, &mut parts, &mut tokens);
+                tokens.push(Token::Semi);
+                i += 1;
+            }
+            _ => {
+                // 새 토큰이 막 시작하는 자리(버퍼가 비어 있음)라면 fd 숫자
+                // 접두사까지 포함해서 리다이렉션 연산자인지 검사하고, 이미
+                // 단어를 쌓고 있는 중이라면 `<`/`>` 글자 자체만 검사한다
+                // (`echo2>out`은 `echo2` 다음에 `>out`이 붙은 것으로 본다).
+                let at_token_start = buf.is_empty() && parts.is_empty();
+                if at_token_start || c == '<' || c == '>' {
+                    if let Some(len) = match_redir_operator(&chars, i, at_token_start) {
+                        flush_word(&mut buf, &mut parts, &mut tokens);
+                        tokens.push(Token::Redir(chars[i..i + len].iter().collect()));
+                        i += len;
+                        continue;
+                    }
+                }
+                buf.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush_word(&mut buf, &mut parts, &mut tokens);
+    Some(tokens)
+}
+
+/// `~`를 `$HOME`으로 확장한다. 단어의 맨 앞(`idx == 0`)에서만 의미가 있고,
+/// `~` 뒤가 비어 있거나 `/`로 시작할 때만 확장한다(`~foo`는 건드리지 않음).
+fn expand_tilde(s: &str) -> String {
+    if let Some(rest) = s.strip_prefix('~') {
+        if rest.is_empty() || rest.starts_with('/') {
+            if let Ok(home) = env::var("HOME") {
+                return format!("{}{}", home, rest);
+            }
+        }
+    }
+    s.to_string()
+}
+
+/// `$VAR`/`${VAR}` 꼴을 `env::var`로 치환한다. 정의되지 않은 변수는 빈
+/// 문자열로 치환되는, 흔한 쉘 동작을 따른다.
+fn expand_vars(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '$' && i + 1 < chars.len() {
+            if chars[i + 1] == '{' {
+                if let Some(end_offset) = chars[i + 2..].iter().position(|&c| c == '}') {
+                    let name: String = chars[i + 2..i + 2 + end_offset].iter().collect();
+                    out.push_str(&env::var(&name).unwrap_or_default());
+                    i = i + 2 + end_offset + 1;
+                    continue;
+                }
+            } else if chars[i + 1].is_alphabetic() || chars[i + 1] == '_' {
+                let start = i + 1;
+                let mut j = start;
+                while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
+                    j += 1;
+                }
+                let name: String = chars[start..j].iter().collect();
+                out.push_str(&env::var(&name).unwrap_or_default());
+                i = j;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+/// 단어를 이루는 조각들을 확장한 뒤 하나의 문자열로 합친다. `~` 확장은
+/// 단어의 첫 조각에만 적용되고, `$VAR` 확장은 따옴표 없는/큰따옴표 조각에만
+/// 적용되며, 작은따옴표·이스케이프로 만들어진 `Literal` 조각은 그대로 둔다.
+fn expand_word(parts: &[WordPart]) -> String {
+    let mut result = String::new();
+    for (idx, part) in parts.iter().enumerate() {
+        match part {
+            WordPart::Literal(s) => result.push_str(s),
+            WordPart::Expandable(s) => {
+                let s = if idx == 0 { expand_tilde(s) } else { s.clone() };
+                result.push_str(&expand_vars(&s));
+            }
+        }
+    }
+    result
+}
+
+/// 토큰 하나의 파이프 구간(리다이렉션이 포함될 수 있는 단일 명령어)을
+/// 파싱하여 프로그램, 인자, 리다이렉션 목록을 추출하는 함수.
+fn parse_command_from_tokens(tokens: &[Token]) -> Option<Command> {
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let mut program = String::new();
+    let mut args = Vec::new();
+    let mut redirects = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Redir(op) => {
+                match parse_redir_operator(op)? {
+                    RedirSpec::ToPath { fd, append, direction } => {
+                        if let Some(Token::Word(target_parts)) = tokens.get(i + 1) {
+                            redirects.push(Redirect {
+                                fd,
+                                target: RedirTarget::Path(expand_word(target_parts)),
+                                append,
+                                direction,
+                            });
+                            i += 2;
+                        } else {
+                            eprintln!("Syntax error: no file after '{}'", op);
+                            return None;
+                        }
+                    }
+                    RedirSpec::ToFd { fd, target_fd } => {
+                        // 이 파서가 인식하는 `N>&M` 형태는 항상 출력 fd를 복제하는
+                        // 용도이므로(`N<&M` 입력 dup은 지원하지 않음) direction은 Out.
+                        redirects.push(Redirect {
+                            fd,
+                            target: RedirTarget::Fd(target_fd),
+                            append: false,
+                            direction: RedirDirection::Out,
+                        });
+                        i += 1;
+                    }
                 }
             }
-            //커멘드 파싱하는 문구
-            // (커멘드) > (커멘드) 의 구조일 것이니까
-            // 토큰에 대해서 arg에 추가하는 것.
-            token => {
+            Token::Word(parts) => {
+                //커멘드 파싱하는 문구
+                // (커멘드) > (커멘드) 의 구조일 것이니까
+                // 토큰에 대해서 arg에 추가하는 것.
+                let word = expand_word(parts);
                 if program.is_empty() {
-                    program = token.to_string();
+                    program = word;
                 } else {
-                    args.push(token.to_string());
+                    args.push(word);
                 }
                 i += 1;
             }
+            Token::Pipe | Token::AndAnd | Token::OrOr | Token::Semi => {
+                unreachable!("pipeline segment should already be split on Pipe/AndAnd/OrOr/Semi")
+            }
         }
     }
 
@@ -79,213 +563,635 @@ fn parse_redir_command(input: &str) -> Option<Command> {
         return None;
     }
 
-    Some(Command {
-        program,
-        args,
-        input_file,
-        output_file,
-    })
+    Some(Command { program, args, redirects })
+}
+
+/// 토큰 목록을 주어진 구분자 토큰(`Token::Pipe`/`Token::Semi`) 기준으로 나눈다.
+fn split_on(tokens: &[Token], is_separator: impl Fn(&Token) -> bool) -> Vec<&[Token]> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, tok) in tokens.iter().enumerate() {
+        if is_separator(tok) {
+            segments.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    segments.push(&tokens[start..]);
+    segments
+}
+
+/// 파이프라인 하나(`Command ('|' Command)*`). `background`는 맨 끝의 `&`가
+/// 붙어 있었는지를 나타내며, 전체 입력에서 제일 마지막 파이프라인에만 붙을 수 있다.
+#[derive(Debug)]
+struct PipelineNode {
+    commands: Vec<Command>,
+    background: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AndOrOp {
+    And,
+    Or,
+}
+
+/// `AndOr = Pipeline (('&&' | '||') Pipeline)*`
+#[derive(Debug)]
+struct AndOrNode {
+    first: PipelineNode,
+    rest: Vec<(AndOrOp, PipelineNode)>,
+}
+
+/// `Pipeline = Command ('|' Command)*`
+fn parse_pipeline(tokens: &[Token]) -> Option<PipelineNode> {
+    let segments = split_on(tokens, |tok| matches!(tok, Token::Pipe));
+    let mut commands = Vec::new();
+    for seg in segments {
+        // 각 세그먼트, 즉 (커멘드)에 대해선
+        // 가장 기본적인 선 처리만 해 두기
+        // 그래도 이 정보 기반으로 다시 실행 가능.
+        commands.push(parse_command_from_tokens(seg)?);
+    }
+    Some(PipelineNode { commands, background: false })
+}
+
+/// `AndOr = Pipeline (('&&' | '||') Pipeline)*`
+fn parse_andor(tokens: &[Token]) -> Option<AndOrNode> {
+    let mut segments = Vec::new();
+    let mut ops = Vec::new();
+    let mut start = 0;
+    for (i, tok) in tokens.iter().enumerate() {
+        match tok {
+            Token::AndAnd => {
+                segments.push(&tokens[start..i]);
+                ops.push(AndOrOp::And);
+                start = i + 1;
+            }
+            Token::OrOr => {
+                segments.push(&tokens[start..i]);
+                ops.push(AndOrOp::Or);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&tokens[start..]);
+
+    let mut segments = segments.into_iter();
+    let first = parse_pipeline(segments.next()?)?;
+    let mut rest = Vec::new();
+    for (op, seg) in ops.into_iter().zip(segments) {
+        rest.push((op, parse_pipeline(seg)?));
+    }
+    Some(AndOrNode { first, rest })
 }
 
-fn parse_input(input: &str) -> Option<InputType> {
-    //재귀적 파싱
+/// `List = AndOr (';' AndOr)*`. 줄 전체를 파싱해서 AST를 만든다. 맨 끝의
+/// `&`는 토큰화 전에 떼어내 두었다가, 파싱이 끝난 뒤 텍스트상 마지막
+/// 파이프라인(`;`나 `&&`/`||`로 몇 단계를 거치든)에 붙여준다.
+fn parse_input(input: &str) -> Option<Vec<AndOrNode>> {
     let input = input.trim();
     if input.is_empty() {
         return None;
     }
 
-    // 파이프 단위로 나누기
-    let pipeline: Vec<&str> = input.split('|').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
-    if pipeline.is_empty() {
+    // `$(...)`/백틱 명령 치환은 토큰화보다 먼저, 줄 전체를 문자 단위로 훑어
+    // 처리한다. 치환된 자식 명령도 다시 이 함수를 타므로 중첩 치환도 지원된다.
+    let substituted = expand_command_substitutions(input)?;
+
+    let (body, background) = strip_background(&substituted);
+
+    let tokens = tokenize(body)?;
+    if tokens.is_empty() {
         return None;
     }
 
-    // 파이프가 하나도 없다면 단일 명령
-    if pipeline.len() == 1 {
-        let cmd = parse_redir_command(pipeline[0])?;
-        // 파이프 없음, 명령 하나에 리다이렉션이 있을 수 있음.
-        match (cmd.input_file.clone(), cmd.output_file.clone()) {
-            // > 입력인 경우
-            (Some(inf), None) => Some(InputType::InputRedirect(cmd, inf)),
-            // < 입력인 경우
-            (None, Some(outf)) => Some(InputType::OutputRedirect(cmd, outf)),
-            (Some(inf), Some(outf)) => {
-                // 재귀적으로 인터프리터 구현해야 하나 했는데, 그정돈 아님
-                // 애초에 과제에서 요구하는 내용도 아니긴 하지만, 파일 리디렉션은 재귀가 무한 깊이가 불가능
-                // 차피 파일에서 끝나므로, 한 단계의 처리, 즉 화살표는 기껏해야 최대 한 개인 한계를 이용.
-                //cat < inp.txt > out.txt 이런 입력에 대한 처리.
-                Some(InputType::BiRedirect(cmd, inf, outf))
-            }
-            //그냥 실행인 경우
-            (None, None) => Some(InputType::SingleCommand(cmd))
+    // `;` 단위로 나누기 (따옴표 안의 `;`/`|`/`&&`/`||`는 Token::Word에 흡수되어 안전함)
+    let segments = split_on(&tokens, |tok| matches!(tok, Token::Semi));
+    let mut list = Vec::new();
+    for seg in segments {
+        list.push(parse_andor(seg)?);
+    }
+
+    if background {
+        if let Some(last_andor) = list.last_mut() {
+            match last_andor.rest.last_mut() {
+                Some((_, pipeline)) => pipeline.background = true,
+                None => last_andor.first.background = true,
+            }
         }
-    } else {
-        // 파이프가 2개 이상 있을 때
-        let mut commands = Vec::new();
-        for seg in pipeline {
-            // 각 세그먼트, 즉 (커멘드)에 대해선
-            // 가장 기본적인 선 처리만 해 두기
-            // 그래도 이 정보 기반으로 다시 실행 가능.
-            let cmd = parse_redir_command(seg)?;
-            commands.push(cmd);
+    }
+
+    Some(list)
+}
+
+/// 잡 테이블/`fg`/`bg` 출력에 쓸 사람이 읽을 수 있는 명령 문자열을 복원한다.
+fn describe_command(cmd: &Command) -> String {
+    let mut parts = vec![cmd.program.clone()];
+    parts.extend(cmd.args.iter().cloned());
+    parts.join(" ")
+}
+
+/// 파이프라인 전체를 `a | b | c` 형태로 복원한다.
+fn describe_pipeline(commands: &[Command]) -> String {
+    commands.iter().map(describe_command).collect::<Vec<_>>().join(" | ")
+}
+
+/// 빌트인이 출력을 써야 할 fd들. 파이프/리다이렉션이 이미 dup2로 실제
+/// 0/1/2번 fd를 바꿔치기해 둔 뒤에 빌트인이 실행되므로, 기본값(1/2)만으로도
+/// 파이프라인 중간/리다이렉션 대상 양쪽 다 올바른 곳에 쓰게 된다.
+#[derive(Debug, Clone, Copy)]
+struct Io {
+    stdout: RawFd,
+    stderr: RawFd,
+}
+
+impl Io {
+    fn std() -> Self {
+        Io { stdout: 1, stderr: 2 }
+    }
+
+    fn write_stdout(&self, s: &str) {
+        write(self.stdout, s.as_bytes()).ok();
+    }
+
+    fn write_stderr(&self, s: &str) {
+        write(self.stderr, s.as_bytes()).ok();
+    }
+}
+
+/// 빌트인 함수 한 종류의 시그니처. 일반 외부 명령과 달리 `execvp` 없이
+/// 셸 프로세스(또는 파이프라인의 자식 프로세스) 안에서 바로 실행된다.
+type BuiltinFn = fn(&Command, Io) -> i32;
+
+fn builtin_cd(cmd: &Command, io: Io) -> i32 {
+    let target = cmd.args.first().map(String::as_str).unwrap_or("/");
+    match env::set_current_dir(target) {
+        Ok(_) => {
+            io.write_stdout(&format!("[oh-my-shell] Changed directory to {}\n", target));
+            0
+        }
+        Err(e) => {
+            io.write_stderr(&format!("cd: {}\n", e));
+            1
+        }
+    }
+}
+
+/// `export NAME=VALUE [NAME=VALUE ...]` 형태로 현재 프로세스(셸)의 환경
+/// 변수를 설정한다. `=`가 없는 인자는 문법 오류로 취급한다.
+fn builtin_export(cmd: &Command, io: Io) -> i32 {
+    for arg in &cmd.args {
+        match arg.split_once('=') {
+            Some((name, value)) => env::set_var(name, value),
+            None => {
+                io.write_stderr(&format!("export: not a valid identifier=value: {}\n", arg));
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+fn builtin_pwd(_cmd: &Command, io: Io) -> i32 {
+    match env::current_dir() {
+        Ok(path) => {
+            io.write_stdout(&format!("{}\n", path.display()));
+            0
+        }
+        Err(e) => {
+            io.write_stderr(&format!("pwd: {}\n", e));
+            1
+        }
+    }
+}
+
+fn builtin_echo(cmd: &Command, io: Io) -> i32 {
+    io.write_stdout(&format!("{}\n", cmd.args.join(" ")));
+    0
+}
+
+/// `exit [code]`. 파이프라인 전체가 이 한 명령뿐일 때만 부모 프로세스에서
+/// 직접 실행되므로, 여기서 `std::process::exit`를 호출하면 정말로 셸이
+/// 끝난다(파이프라인 중간에 섞여 자식 프로세스에서 실행됐다면 그 자식만 끝남).
+fn builtin_exit(cmd: &Command, _io: Io) -> i32 {
+    let code = cmd.args.first().and_then(|s| s.parse().ok()).unwrap_or(0);
+    println!("Exit oh-my-shell. Bye!");
+    std::process::exit(code);
+}
+
+/// 이름으로 빌트인 함수를 찾는다. `cd`/`export`/`pwd`/`echo`/`exit` 다섯
+/// 가지만 지원하며, 나머지는 전부 `execvp`로 넘어가는 외부 명령이다.
+fn find_builtin(name: &str) -> Option<BuiltinFn> {
+    match name {
+        "cd" => Some(builtin_cd),
+        "export" => Some(builtin_export),
+        "pwd" => Some(builtin_pwd),
+        "echo" => Some(builtin_echo),
+        "exit" => Some(builtin_exit),
+        _ => None,
+    }
+}
+
+/// 셸 자신의 상태(작업 디렉터리, 환경 변수, 생존 여부)를 바꾸는 빌트인.
+/// 파이프라인 자식 프로세스 안에서 실행하면 효과가 부모에 남지 않으므로,
+/// 파이프라인이 이 한 명령뿐일 때는 포크 없이 부모에서 직접 실행해야 한다.
+fn is_stateful_builtin(name: &str) -> bool {
+    matches!(name, "cd" | "export" | "exit")
+}
+
+/// 리다이렉션 목록을 순서대로 적용한다. 순서가 중요한데, 예를 들어
+/// `2>&1 1>out`은 먼저 stderr를 (옛) stdout으로 돌린 뒤 stdout을 바꾸므로
+/// stderr는 터미널에 남고, `1>out 2>&1`은 반대로 stderr까지 out으로 간다.
+/// 외부 명령(`run_single_command`)과 파이프라인 안에서 실행되는 빌트인
+/// 양쪽 모두 이 함수로 자신의 리다이렉션을 적용한다.
+fn apply_redirects(redirects: &[Redirect]) {
+    for redirect in redirects {
+        match &redirect.target {
+            RedirTarget::Path(path) => {
+                let file = match redirect.direction {
+                    RedirDirection::In => File::open(path).expect("Failed to open file for reading"),
+                    RedirDirection::Out if redirect.append => OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .expect("Failed to open file for append"),
+                    RedirDirection::Out => File::create(path).expect("Failed to create output file"),
+                };
+                dup2(file.as_raw_fd(), redirect.fd).expect("Failed to redirect fd");
+            }
+            RedirTarget::Fd(target_fd) => {
+                dup2(*target_fd, redirect.fd).expect("Failed to dup fd");
+            }
         }
-        // 여기서는 파이프 벡터를 반환. 각 명령은 run_single_command에서 input/output_file을 처리 가능
-        //파이프는 (커멘드) | (커멘드) | (커멘드)의 형태로 처리
-        Some(InputType::Pipe(commands))
     }
 }
 
-fn run_single_command(cmd: &Command, input_file: Option<&str>, output_file: Option<&str>) {
+/// 포크 없이 부모 프로세스 안에서 리다이렉션을 적용하기 전에, `redirect.fd`
+/// 각각이 지금 가리키는 곳을 `dup`으로 복사해 둔다. `restore_fds`로 되돌리기
+/// 전까지는 이 복사본을 통해 원래 fd를 그대로 쓸 수 있다.
+fn save_fds(redirects: &[Redirect]) -> Vec<(RawFd, RawFd)> {
+    redirects
+        .iter()
+        .map(|r| (r.fd, dup(r.fd).expect("Failed to save fd before builtin redirection")))
+        .collect()
+}
+
+/// `save_fds`로 저장해 둔 원래 fd들을 제자리로 되돌리고 복사본을 닫는다.
+fn restore_fds(saved: Vec<(RawFd, RawFd)>) {
+    for (fd, saved_fd) in saved {
+        dup2(saved_fd, fd).expect("Failed to restore fd after builtin redirection");
+        close(saved_fd).ok();
+    }
+}
+
+/// 리다이렉션을 적용한 뒤 execvp로 넘어간다.
+fn run_single_command(cmd: &Command) {
     let c_program = CString::new(cmd.program.as_str()).expect("CString failed");
     let mut c_args: Vec<CString> = Vec::new();
     c_args.push(c_program.clone());
     c_args.extend(cmd.args.iter().map(|arg| CString::new(arg.as_str()).unwrap()));
-    
 
-    //여기서 쌍방향도 처리 가능.
-    let infile = input_file.or(cmd.input_file.as_deref());
-    let outfile = output_file.or(cmd.output_file.as_deref());
-    
-    //두 단계 연속으로 처리하게 하면 됨.
-    // 파일 구조의 한계 덕분.
-    if let Some(file) = infile {
-        let input_fd = File::open(file).expect("Failed to open input file");
-        dup2(input_fd.as_raw_fd(), 0).expect("Failed to redirect input");
-    }
-    if let Some(file) = outfile {
-        let output_fd = File::create(file).expect("Failed to create output file");
-        dup2(output_fd.as_raw_fd(), 1).expect("Failed to redirect output");
-    }
+    apply_redirects(&cmd.redirects);
 
     execvp(&c_program, &c_args).expect("Failed to execute command");
 }
 
-fn main() {
-    println!("######### oh-my-shell starts! #########");
+/// 잡 하나의 실행 상태. `Done`이 된 잡은 다음 프롬프트에서 한 번 보고된 뒤
+/// 잡 테이블에서 제거된다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Running,
+    Stopped,
+    Done,
+}
 
-    loop {
-        print!(">>> ");
-        io::stdout().flush().expect("Failed to flush stdout");
+#[derive(Debug, Clone)]
+struct Job {
+    id: usize,
+    pgid: Pid,
+    command_line: String,
+    state: JobState,
+    pids: Vec<Pid>,
+}
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read line");
-        let input = input.trim();
+/// 백그라운드/정지된 잡들을 추적하는 테이블. `SIGCHLD` 핸들러 대신, 매
+/// 프롬프트를 찍기 전에 `reap_background`를 논블로킹으로 폴링해서 상태를
+/// 갱신한다.
+struct JobTable {
+    jobs: Vec<Job>,
+    next_id: usize,
+}
+
+impl JobTable {
+    fn new() -> Self {
+        JobTable { jobs: Vec::new(), next_id: 1 }
+    }
+
+    fn add(&mut self, pgid: Pid, pids: Vec<Pid>, command_line: String, state: JobState) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(Job { id, pgid, command_line, state, pids });
+        id
+    }
 
-        if input == "exit" {
-            println!("Exit oh-my-shell. Bye!");
-            break;
+    fn find_by_spec(&mut self, spec: &str) -> Option<&mut Job> {
+        let id: usize = spec.trim_start_matches('%').parse().ok()?;
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    fn mark_pid_done(&mut self, pid: Pid) {
+        for job in &mut self.jobs {
+            if let Some(pos) = job.pids.iter().position(|p| *p == pid) {
+                job.pids.remove(pos);
+                if job.pids.is_empty() {
+                    job.state = JobState::Done;
+                }
+            }
         }
+    }
 
-        //cd도 걍 구현해봄. 근데 굳이 구현 필욘 x.
-        if input.starts_with("cd") {
-            let parts: Vec<&str> = input.split_whitespace().collect();
-            let new_dir = parts.get(1).unwrap_or(&"/");
-            match env::set_current_dir(new_dir) {
-                Ok(_) => println!("[oh-my-shell] Changed directory to {}", new_dir),
-                Err(e) => eprintln!("cd: {}", e),
+    fn mark_pid_state(&mut self, pid: Pid, state: JobState) {
+        for job in &mut self.jobs {
+            if job.pids.contains(&pid) {
+                job.state = state;
             }
-            continue;
         }
+    }
 
-        let parsed_input = parse_input(input);
-        if let Some(input_type) = parsed_input {
-            match input_type {
-                // 결국 모든 것은 커멘드의 조합임. 커멘드 실행 이후 방향 컨트롤의 문제
-                // 파이프를 쓰지 않고, 파일에 의존하는 경우엔 싱긒_커멘드 인수 컨트롤로 충분.
-                InputType::SingleCommand(cmd) => {
-                    handle_single_command(cmd, None, None);
-                }
-                InputType::InputRedirect(cmd, file) => {
-                    handle_single_command(cmd, Some(&file), None);
+    /// `waitpid(-1, WNOHANG|WUNTRACED)`를 바닥날 때까지 반복해서, 백그라운드로
+    /// 돌던 자식 중 끝났거나 멈춘 것들의 상태를 한 번에 반영한다.
+    fn reap_background(&mut self) {
+        loop {
+            match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED)) {
+                Ok(WaitStatus::Exited(pid, _)) | Ok(WaitStatus::Signaled(pid, _, _)) => {
+                    self.mark_pid_done(pid);
                 }
-                InputType::OutputRedirect(cmd, file) => {
-                    handle_single_command(cmd, None, Some(&file));
+                Ok(WaitStatus::Stopped(pid, _)) => {
+                    self.mark_pid_state(pid, JobState::Stopped);
                 }
-                InputType::BiRedirect(cmd, inf, outf) => {
-                    handle_single_command(cmd,Some(&inf), Some(&outf));
+                Ok(WaitStatus::Continued(pid)) => {
+                    self.mark_pid_state(pid, JobState::Running);
                 }
-                
-                //결국, 파이프가 아닌 싱글 커멘드들은 전부 위에서 처리됨
-                // 여기서부턴 파이프를 이용
-                // 근데, 파이프는 위의 로직을 걍 반복해주면 끝
-                InputType::Pipe(commands) => {
-                    handle_pipes(commands);
+                Ok(WaitStatus::StillAlive) => break,
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        self.report_finished();
+    }
+
+    fn report_finished(&mut self) {
+        for job in self.jobs.iter().filter(|j| j.state == JobState::Done) {
+            println!("[{}]+  Done                    {}", job.id, job.command_line);
+        }
+        self.jobs.retain(|job| job.state != JobState::Done);
+    }
+
+    fn list(&self) {
+        for job in &self.jobs {
+            let label = match job.state {
+                JobState::Running => "Running",
+                JobState::Stopped => "Stopped",
+                JobState::Done => "Done",
+            };
+            println!("[{}]+  {}                 {}", job.id, label, job.command_line);
+        }
+    }
+
+    /// 정지된 잡을 전경으로 가져와 재개시키고, 다시 끝나거나 멈출 때까지 기다린다.
+    fn fg(&mut self, spec: &str, shell_pgid: Pid) {
+        let Some(job) = self.find_by_spec(spec) else {
+            eprintln!("fg: no such job: {}", spec);
+            return;
+        };
+        let pgid = job.pgid;
+        let command_line = job.command_line.clone();
+        let mut remaining = job.pids.len();
+        println!("{}", command_line);
+
+        signal::kill(Pid::from_raw(-pgid.as_raw()), Signal::SIGCONT).ok();
+        tcsetpgrp(io::stdin().as_raw_fd(), pgid).ok();
+
+        let mut stopped_again = false;
+        while remaining > 0 {
+            match waitpid(Pid::from_raw(-pgid.as_raw()), Some(WaitPidFlag::WUNTRACED)) {
+                Ok(WaitStatus::Exited(_, _)) | Ok(WaitStatus::Signaled(_, _, _)) => remaining -= 1,
+                Ok(WaitStatus::Stopped(_, _)) => {
+                    stopped_again = true;
+                    break;
                 }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        tcsetpgrp(io::stdin().as_raw_fd(), shell_pgid).ok();
 
+        if stopped_again {
+            if let Some(job) = self.find_by_spec(spec) {
+                job.state = JobState::Stopped;
             }
+        } else {
+            self.jobs.retain(|job| job.pgid != pgid);
         }
     }
+
+    /// 정지된 잡을 백그라운드에서 계속 돌아가게 재개시킨다.
+    fn bg(&mut self, spec: &str) {
+        let Some(job) = self.find_by_spec(spec) else {
+            eprintln!("bg: no such job: {}", spec);
+            return;
+        };
+        println!("[{}] {}", job.id, job.command_line);
+        signal::kill(Pid::from_raw(-job.pgid.as_raw()), Signal::SIGCONT).ok();
+        job.state = JobState::Running;
+    }
 }
 
-fn handle_single_command(cmd: Command, input_file: Option<&str>, output_file: Option<&str>) {
-    match unsafe { fork() } {
-        Ok(ForkResult::Child) => {
-            run_single_command(&cmd, input_file, output_file);
+/// 쉘을 자신만의 전경 프로세스 그룹으로 세팅하고, 터미널 제어 시그널
+/// (`SIGTTOU`/`SIGTTIN`/`SIGTSTP`)을 무시한다. 이렇게 해야 이후
+/// `tcsetpgrp`로 터미널을 자식 잡에게 넘겼다가 되찾아오는 동안 쉘 자신이
+/// 멈추지 않는다.
+fn init_shell() -> Pid {
+    let shell_pgid = getpid();
+
+    unsafe {
+        let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+        signal::sigaction(Signal::SIGTTOU, &ignore).expect("Failed to ignore SIGTTOU");
+        signal::sigaction(Signal::SIGTTIN, &ignore).expect("Failed to ignore SIGTTIN");
+        signal::sigaction(Signal::SIGTSTP, &ignore).expect("Failed to ignore SIGTSTP");
+    }
+
+    setpgid(Pid::from_raw(0), shell_pgid).ok();
+    if tcgetpgrp(io::stdin().as_raw_fd()).map(|pg| pg != shell_pgid).unwrap_or(false) {
+        tcsetpgrp(io::stdin().as_raw_fd(), shell_pgid).ok();
+    }
+
+    shell_pgid
+}
+
+/// 맨 끝의 `&`를 떼어내 "백그라운드로 돌려라"는 표시와 나머지 명령 줄로 나눈다.
+fn strip_background(input: &str) -> (&str, bool) {
+    let trimmed = input.trim_end();
+    match trimmed.strip_suffix('&') {
+        Some(rest) => (rest.trim_end(), true),
+        None => (trimmed, false),
+    }
+}
+
+fn main() {
+    println!("######### oh-my-shell starts! #########");
+
+    let shell_pgid = init_shell();
+    let mut jobs = JobTable::new();
+
+    loop {
+        jobs.reap_background();
+
+        print!(">>> ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        let input = input.trim();
+
+        // 잡 컨트롤 빌트인: jobs / fg %n / bg %n
+        // (cd/export/pwd/echo/exit는 빌트인 레지스트리를 통해 run_pipeline
+        // 안에서 처리된다: 이 한 명령뿐인 파이프라인일 때 상태 변경 빌트인은
+        // 포크 없이 부모에서 직접 실행된다.)
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.as_slice() {
+            ["jobs"] => {
+                jobs.list();
+                continue;
+            }
+            ["fg", spec] => {
+                jobs.fg(spec, shell_pgid);
+                continue;
+            }
+            ["bg", spec] => {
+                jobs.bg(spec);
+                continue;
+            }
+            _ => {}
         }
-        Ok(ForkResult::Parent { child }) => {
-            match waitpid(child, None).expect("Failed to wait for child") {
-                WaitStatus::Exited(pid, status) => {
-                    println!("[oh-my-shell] Child process terminated: pid {}, status {}", pid, status);
-                }
-                WaitStatus::Signaled(pid, signal, _) => {
-                    println!("[oh-my-shell] Child process terminated by signal: pid {}, signal {:?}", pid, signal);
+
+        // `List = AndOr (';' AndOr)*`를 파싱한 뒤 앞에서부터 순서대로
+        // 실행한다. `;`로 이어진 구문은 항상 전부 실행되고, `&&`/`||`로
+        // 이어진 구문은 바로 앞 파이프라인의 종료 상태에 따라 건너뛸 수도
+        // 있다 (eval_list/eval_andor가 단락 평가를 담당).
+        if let Some(list) = parse_input(input) {
+            eval_list(&list, &mut jobs, shell_pgid);
+        }
+    }
+}
+
+/// 파이프 자식 프로세스 안에서 이전 단계의 읽기 끝과 다음 단계로 가는
+/// 쓰기 끝을 각각 표준 입력/출력(0/1)에 연결한다. 이 단계 자신의 파일
+/// 리다이렉션은 여기서 건드리지 않고, 호출자가 그 뒤에 `apply_redirects`로
+/// (외부 명령이면 `run_single_command`를 통해) 덮어쓰게 한다.
+fn wire_pipe_fds(prev_pipe: Option<RawFd>, r: Option<RawFd>, w: Option<RawFd>) {
+    if let Some(fd) = prev_pipe {
+        dup2(fd, 0).expect("Failed to dup2 input");
+        close(fd).expect("Failed to close old input fd");
+    }
+    if let Some(fd) = w {
+        dup2(fd, 1).expect("Failed to dup2 output");
+        close(fd).expect("Failed to close write end of pipe");
+    }
+    if let Some(fd) = r {
+        close(fd).expect("Failed to close read end of pipe in child");
+    }
+}
+
+/// 파이프라인(길이 1짜리 싱글 커멘드 포함) 하나를 자신만의 프로세스 그룹으로
+/// 실행한다. 전경 잡이면 터미널을 그 그룹에게 넘기고 그룹 전체가 끝나거나
+/// 멈출 때까지 기다린 뒤 터미널을 쉘에게 되찾아오고, 백그라운드 잡이면
+/// 잡 테이블에 등록만 하고 바로 프롬프트로 돌아간다.
+///
+/// `&&`/`||`로 다음 파이프라인을 이어갈지 판단할 수 있도록, 파이프라인의
+/// 마지막 단계가 남긴 종료 상태를 셸 관례(정상 종료는 종료 코드 그대로,
+/// 시그널에 의한 종료는 `128 + 시그널 번호`)로 변환해 반환한다. 백그라운드로
+/// 돌렸거나 멈춘 잡은 아직 끝난 것이 아니므로 `0`(성공)으로 취급한다.
+fn run_pipeline(commands: Vec<Command>, background: bool, jobs: &mut JobTable, shell_pgid: Pid) -> i32 {
+    // `cd`/`export`/`exit`처럼 셸 자신의 상태를 바꾸는 빌트인은, 파이프라인이
+    // 이 한 명령뿐이고 백그라운드도 아닐 때만 포크 없이 부모 프로세스에서
+    // 직접 실행한다. 그래야 작업 디렉터리 변경이나 환경 변수가 다음
+    // 프롬프트에도 남는다. 여러 단계 파이프라인 안에 섞여 있거나
+    // (`cd /tmp | echo`) `&`로 백그라운드로 돌리면(`cd /tmp &`) 그냥 다른
+    // 빌트인처럼 자식 프로세스에서 실행되어 효과가 사라지는데, 이는 실제
+    // 쉘에서도 마찬가지다(백그라운드 잡은 어차피 자신만의 프로세스가 필요함).
+    if !background {
+        if let [only] = commands.as_slice() {
+            if is_stateful_builtin(&only.program) {
+                if let Some(f) = find_builtin(&only.program) {
+                    // 부모 프로세스 자신의 fd를 건드리는 것이므로, 끝나고
+                    // 반드시 원래대로 되돌려야 셸의 진짜 터미널 입출력이
+                    // 리다이렉션된 채로 남지 않는다.
+                    let saved = save_fds(&only.redirects);
+                    apply_redirects(&only.redirects);
+                    let status = f(only, Io::std());
+                    restore_fds(saved);
+                    return status;
                 }
-                _ => println!("[oh-my-shell] Child process ended unexpectedly."),
             }
         }
-        Err(e) => eprintln!("Fork failed: {}", e),
     }
-}
 
-fn handle_pipes(commands: Vec<Command>) {
+    let command_line = describe_pipeline(&commands);
     let mut prev_pipe: Option<RawFd> = None;
-    let mut children = Vec::new();
+    let mut pids: Vec<Pid> = Vec::new();
+    let mut pgid: Option<Pid> = None;
 
     for (i, cmd) in commands.iter().enumerate() {
-        //커멘드 순회하면서 파이프 생성 여부 결정
-        // 끝에선 당연히 없음.
         let (r, w) = if i < commands.len() - 1 {
             let (r, w) = pipe().expect("Failed to create pipe");
-            let rfd = r.into_raw_fd();
-            let wfd = w.into_raw_fd();
-            (Some(rfd), Some(wfd))
+            (Some(r.into_raw_fd()), Some(w.into_raw_fd()))
         } else {
             (None, None)
         };
 
         match unsafe { fork() } {
             Ok(ForkResult::Child) => {
-                // 대충 조건문으로 모든 케이스 검사
-                // 현재 배열은 (커멘드), (커멘드), (커멘드)인데,
-                // prev_pipe를 바탕으로 읽어서, write로 적은 후, r로 write이후를 포인팅.
-                //파이프 모델 떠올리면 편함.
-                // 파이프를 연결짓는단 마인드니까. 당연히 r은 prev로 갈 꺼고.
-                // prev-(w-r)로 호출
-
-                if let Some(fd) = prev_pipe {
-                    dup2(fd, 0).expect("Failed to dup2 input");
-                    close(fd).expect("Failed to close old input fd");
-                }
-                if let Some(fd) = w {
-                    dup2(fd, 1).expect("Failed to dup2 output");
-                    close(fd).expect("Failed to close write end of pipe");
+                // 그룹의 첫 자식은 스스로 리더가 되고(setpgid(0,0)), 이후
+                // 자식들은 리더의 그룹에 합류한다(setpgid(0, leader_pid)).
+                let this_pgid = pgid.unwrap_or(Pid::from_raw(0));
+                setpgid(Pid::from_raw(0), this_pgid).ok();
+
+                // 잡 컨트롤 관련 시그널은 기본 동작으로 되돌려 자식이
+                // Ctrl-Z 등으로 멈추거나 끊길 수 있게 한다.
+                unsafe {
+                    let default = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+                    signal::sigaction(Signal::SIGTTOU, &default).ok();
+                    signal::sigaction(Signal::SIGTTIN, &default).ok();
+                    signal::sigaction(Signal::SIGTSTP, &default).ok();
                 }
-                if let Some(fd) = r {
-                    close(fd).expect("Failed to close read end of pipe in child");
+
+                wire_pipe_fds(prev_pipe, r, w);
+                // 파이프로 이어받은 표준 입출력 위에 이 단계 자신의 리다이렉션을
+                // 덮어쓴다. 예를 들어 `cat < in.txt | grep x > out.txt`의 첫 단계는
+                // 파이프의 쓰기 끝을 stdout으로 받은 뒤(wire_pipe_fds) `< in.txt`가
+                // stdin을 다시 파일로 바꾸고(apply_redirects), 둘째 단계는 파이프의
+                // 읽기 끝을 stdin으로 받은 뒤 `> out.txt`가 stdout을 파일로 바꾼다.
+                // apply_redirects가 방향을 정확히 구분하므로 이 조합이 올바르게
+                // 동작한다.
+                match find_builtin(&cmd.program) {
+                    Some(f) => {
+                        apply_redirects(&cmd.redirects);
+                        std::process::exit(f(cmd, Io::std()));
+                    }
+                    None => {
+                        run_single_command(cmd);
+                        std::process::exit(0);
+                    }
                 }
-                
-                //해당 포인터를 바탕으로 커멘드 하나 실행
-                run_single_command(cmd, None, None);
-                std::process::exit(0);
             }
             Ok(ForkResult::Parent { child }) => {
-
-                //위의 pre_pipe에 대한 if에서 이미 동기성이 만족.
-                // pre_pipe는 이전의 r인데, 이전의 r은 이전의 w에 대한 블로킹 상태
-                // 지금의 w는 지금의 prev_pipe이후 실행, prev_pipe는 이전의 r, 이전의 r은 이전의 w의존
-                //그러니 자동으로 w->r->w-> 순서가 유지
-                //굳이 wait필요없음.
-                children.push(child);
+                // 부모도 동일한 setpgid를 반복해서, 자식이 아직 그룹을
+                // 세팅하기 전에 부모가 tcsetpgrp를 호출하는 레이스를 피한다.
+                let this_pgid = pgid.unwrap_or(child);
+                setpgid(child, this_pgid).ok();
+                pgid = Some(this_pgid);
+                pids.push(child);
 
                 if let Some(fd) = w {
                     close(fd).expect("Failed to close write fd in parent");
@@ -300,24 +1206,86 @@ fn handle_pipes(commands: Vec<Command>) {
         close(fd).expect("Failed to close last pipe read end in parent");
     }
 
+    let pgid = match pgid {
+        Some(pgid) => pgid,
+        None => return 0, // fork가 전부 실패해서 띄운 프로세스가 없음
+    };
 
-    // 모든 자식의 종료 상태를 여기서 수집
-    let mut results = Vec::new();
-    for child in &children {
-        let status = waitpid(*child, None).expect("Failed to wait for child");
-        results.push(status);
+    if background {
+        let id = jobs.add(pgid, pids, command_line, JobState::Running);
+        println!("[{}] {}", id, pgid);
+        return 0;
     }
 
+    tcsetpgrp(io::stdin().as_raw_fd(), pgid).ok();
 
-    for status in results {
-        match status {
-            WaitStatus::Exited(pid, code) => {
-                println!("[oh-my-shell] Child process terminated: pid {}, status {}", pid, code);
+    let mut remaining = pids.len();
+    let mut stopped = false;
+    let mut exit_codes: HashMap<Pid, i32> = HashMap::new();
+    while remaining > 0 {
+        match waitpid(Pid::from_raw(-pgid.as_raw()), Some(WaitPidFlag::WUNTRACED)) {
+            Ok(WaitStatus::Exited(pid, status)) => {
+                println!("[oh-my-shell] Child process terminated: pid {}, status {}", pid, status);
+                exit_codes.insert(pid, status);
+                remaining -= 1;
             }
-            WaitStatus::Signaled(pid, signal, _) => {
+            Ok(WaitStatus::Signaled(pid, signal, _)) => {
                 println!("[oh-my-shell] Child process terminated by signal: pid {}, signal {:?}", pid, signal);
+                exit_codes.insert(pid, 128 + signal as i32);
+                remaining -= 1;
             }
-            _ => println!("[oh-my-shell] Child process ended unexpectedly."),
+            Ok(WaitStatus::Stopped(_, _)) => {
+                stopped = true;
+                break;
+            }
+            Ok(_) => {}
+            Err(_) => break,
         }
     }
+
+    // 전경이었던 그룹 대신 쉘 자신을 다시 터미널의 주인으로 되돌린다.
+    tcsetpgrp(io::stdin().as_raw_fd(), shell_pgid).ok();
+
+    if stopped {
+        let id = jobs.add(pgid, pids, command_line.clone(), JobState::Stopped);
+        println!("[{}]+  Stopped                 {}", id, command_line);
+        return 0;
+    }
+
+    // 파이프라인의 종료 상태는 마지막 단계(`cmd1 | cmd2 | cmd3`라면 cmd3)의
+    // 것을 따른다. 대기 도중 오류가 나서 기록하지 못했다면 0으로 취급한다.
+    pids.last().and_then(|pid| exit_codes.get(pid)).copied().unwrap_or(0)
+}
+
+/// `Pipeline = Command ('|' Command)*` 하나를 실행한다.
+fn eval_pipeline(node: &PipelineNode, jobs: &mut JobTable, shell_pgid: Pid) -> i32 {
+    run_pipeline(node.commands.clone(), node.background, jobs, shell_pgid)
+}
+
+/// `AndOr = Pipeline (('&&' | '||') Pipeline)*`를 단락 평가로 실행한다.
+/// `&&`는 왼쪽이 성공(0)했을 때만, `||`는 왼쪽이 실패(0이 아님)했을 때만
+/// 오른쪽을 실행한다.
+fn eval_andor(node: &AndOrNode, jobs: &mut JobTable, shell_pgid: Pid) -> i32 {
+    let mut status = eval_pipeline(&node.first, jobs, shell_pgid);
+    for (op, pipeline) in &node.rest {
+        let should_run = match op {
+            AndOrOp::And => status == 0,
+            AndOrOp::Or => status != 0,
+        };
+        if should_run {
+            status = eval_pipeline(pipeline, jobs, shell_pgid);
+        }
+    }
+    status
+}
+
+/// `List = AndOr (';' AndOr)*`. `;`로 이어진 구문들은 서로의 종료 상태와
+/// 무관하게 순서대로 전부 실행된다. 전체 리스트의 종료 상태는 마지막
+/// 구문의 것을 따른다.
+fn eval_list(list: &[AndOrNode], jobs: &mut JobTable, shell_pgid: Pid) -> i32 {
+    let mut status = 0;
+    for node in list {
+        status = eval_andor(node, jobs, shell_pgid);
+    }
+    status
 }